@@ -7,19 +7,62 @@ use crate::{
 use bytes::{BufMut, Bytes, BytesMut};
 use serialport::{ClearBuffer, DataBits, SerialPort, StopBits};
 use sha2::{Digest, Sha256};
-use std::{thread, time::Duration};
+use std::{thread, time::{Duration, Instant}};
 
 pub use crate::command::KeyType;
 
+/// A single wire-level event emitted through the trace hook installed with
+/// [`Ecc::set_trace`]. The slices borrow the driver's scratch buffers and are
+/// only valid for the duration of the callback; the command/response opcode is
+/// the relevant byte of the decoded UART payload.
+pub enum TraceEvent<'a> {
+    /// UART bytes and the SWI-encoded form they were expanded into.
+    Encode { uart: &'a [u8], swi: &'a [u8] },
+    /// SWI-encoded bytes written to the wire.
+    Send(&'a [u8]),
+    /// SWI-encoded bytes read back from the wire.
+    Recv(&'a [u8]),
+    /// SWI-encoded bytes and the UART bytes recovered from them.
+    Decode { swi: &'a [u8], uart: &'a [u8] },
+}
+
 pub struct Ecc {
     port: String,
+    uart: Option<Box<dyn SerialPort>>,
+    framing: Option<Framing>,
+    spin_delays: bool,
+    trace: Option<Box<dyn FnMut(TraceEvent) + Send>>,
+}
+
+/// The UART line setup currently applied to the cached handle. The wake token
+/// is a break-like pulse that needs the slower 8N1 framing, while every command
+/// exchange runs at the fast 7N1 setting; tracking the active mode lets the
+/// driver re-issue `set_baud_rate`/`set_data_bits` only when it actually changes.
+#[derive(Clone, Copy, PartialEq)]
+enum Framing {
+    /// 8N1 at 115200 baud — used only for the wake pulse.
+    Wake,
+    /// 7N1 at 230400 baud — used for every command/response exchange.
+    Command,
 }
 
 pub const MAX_SLOT: u8 = 15;
 
 pub(crate) const RECV_RETRIES: u8 = 2;
 pub(crate) const RECV_RETRY_WAIT: Duration = Duration::from_millis(50);
-pub(crate) const CMD_RETRIES: u8 = 10; 
+pub(crate) const CMD_RETRIES: u8 = 10;
+
+/// One UART byte at 230400 baud with 7N1 framing (9 bits) takes ~39 µs; each
+/// protocol byte is SWI-encoded as 8 of these cells. The receive path uses
+/// this to decide when the bus has gone idle and the frame is complete.
+pub(crate) const UART_BYTE_TIME: Duration = Duration::from_micros(39);
+
+/// Per-`read` blocking timeout for the receive loop. The idle-line threshold is
+/// only a couple of byte-times (~78 µs), but `serialport` rounds the port
+/// timeout down to whole milliseconds for `poll()`, so anything below 1 ms
+/// floors to a non-blocking read and spins a core. A small blocking timeout lets
+/// the loop sleep in `poll()` between bytes while still reacting promptly.
+pub(crate) const READ_POLL_TIMEOUT: Duration = Duration::from_millis(2);
 
 impl Ecc {
     pub fn from_path(path: &str, address: u16) -> Result<Self> {
@@ -27,7 +70,96 @@ impl Ecc {
         let _ = address; //keep the API the same. Address refers to i2c addr which isn't required for SWI
         let port = String::from( path );
 
-        Ok(Self {port})
+        Ok(Self { port, uart: None, framing: None, spin_delays: true, trace: None })
+    }
+
+    /// Installs a callback that receives every [`TraceEvent`] the driver emits,
+    /// so a failing `sign`/`write` can be traced without patching the library.
+    /// `Send`/`Recv` cover the command frames written by `send_buf` and the
+    /// responses read by `recv_buf`; the wake token, the wake-response read,
+    /// the sleep token and the transmit-flag polls are not reported as
+    /// `Send`/`Recv` (the wake token produces no event at all). Pass a fresh
+    /// closure to replace an existing hook.
+    pub fn set_trace(&mut self, trace: impl FnMut(TraceEvent) + Send + 'static) {
+        self.trace = Some(Box::new(trace));
+    }
+
+    fn emit_trace(&mut self, event: TraceEvent) {
+        if let Some(trace) = self.trace.as_mut() {
+            trace(event);
+        }
+    }
+
+    /// Selects how sub-millisecond delays are taken. When `true` (the default)
+    /// they are busy-waited, which keeps the bit-banged SWI waveform stable on
+    /// a stock Linux kernel where `thread::sleep` routinely overshoots a
+    /// microsecond request to 1–4 ms. Callers on a real-time kernel can pass
+    /// `false` to yield the CPU instead.
+    pub fn set_spin_delays(&mut self, spin: bool) {
+        self.spin_delays = spin;
+    }
+
+    /// Waits for `interval`, busy-spinning on [`Instant`] when spin delays are
+    /// enabled and falling back to [`thread::sleep`] otherwise. Intended for
+    /// the tight waits below ~1 ms; longer waits should use `thread::sleep`
+    /// directly.
+    fn spin_us(&self, interval: Duration) {
+        if self.spin_delays {
+            let start = Instant::now();
+            while start.elapsed() < interval {}
+        } else {
+            thread::sleep(interval);
+        }
+    }
+
+    /// Returns the owned UART handle, opening it on first use and reusing the
+    /// cached handle thereafter. The handle is taken out of `self` so the
+    /// serial I/O helpers can borrow it alongside `&mut self`; callers must
+    /// put it back into `self.uart` once they are done with it.
+    fn open_uart(&mut self) -> Result<Box<dyn SerialPort>> {
+        match self.uart.take() {
+            Some(uart) => Ok(uart),
+            None => {
+                let uart = serialport::new(&self.port, 230_400)
+                    .stop_bits(StopBits::One)
+                    .data_bits(DataBits::Seven)
+                    .open()?;
+                // A freshly opened handle carries the constructor's 7N1 setup,
+                // but record the mode as unknown so the first exchange re-asserts
+                // it explicitly rather than trusting the builder defaults.
+                self.framing = None;
+                Ok(uart)
+            }
+        }
+    }
+
+    /// Applies `framing` to the cached handle, skipping the `set_baud_rate`/
+    /// `set_data_bits` syscalls when the line is already in that mode. Callers
+    /// own `uart` for the duration of a transaction, so the tracked state stays
+    /// in step with the handle.
+    fn set_framing(&mut self, uart: &mut Box<dyn SerialPort>, framing: Framing) -> Result {
+        if self.framing == Some(framing) {
+            return Ok(());
+        }
+        // Clear the tracker first: the switch is two syscalls, and if the second
+        // fails the line is left half-configured. Recording "unknown" until both
+        // succeed forces the next call to re-assert the full setup rather than
+        // trusting a mode that was only partially applied.
+        self.framing = None;
+        match framing {
+            // The wake token drops to 8N1 so the single zero byte reads as a
+            // break-like low pulse.
+            Framing::Wake => {
+                uart.set_baud_rate(115_200)?;
+                uart.set_data_bits(DataBits::Eight)?;
+            }
+            Framing::Command => {
+                uart.set_baud_rate(230_400)?;
+                uart.set_data_bits(DataBits::Seven)?;
+            }
+        }
+        self.framing = Some(framing);
+        Ok(())
     }
 
     pub fn get_info(&mut self) -> Result<Bytes> {
@@ -127,7 +259,7 @@ impl Ecc {
             match response{
                 Ok(_) => (),
                 Err(_) if attempts < 3 => {
-                    self.send_sleep();
+                    let _ = self.send_sleep();
                     continue;
                 }
                 Err(_) => return response,
@@ -141,7 +273,7 @@ impl Ecc {
             match response{
                 Ok(_) => return response,
                 Err(_) if attempts < 3 => {
-                    self.send_sleep();
+                    let _ = self.send_sleep();
                     continue;
                 }
                 Err(_) => return response,
@@ -178,80 +310,70 @@ impl Ecc {
     }
 
     fn send_wake(&mut self) -> Result {
-        let port_name = &self.port;
-        let baud_rate = 115_200;
-        let stop_bits = StopBits::One;
-        let data_bits = DataBits::Eight;
-        let uart_wake_builder = serialport::new(port_name, baud_rate)
-            .stop_bits(stop_bits)
-            .data_bits(data_bits);
-
-        let mut uart_wake = uart_wake_builder.open().unwrap_or_else(|e| {
-            eprintln!("Failed to open port {}. Error: {}", port_name,e);
-            ::std::process::exit(1);
-        });
-        let _ = uart_wake.write(&[0]);
-        
+        // The wake token is a break-like low pulse, so the line has to drop to
+        // the slower 8N1 framing for the duration of the single zero byte.
+        let mut uart = self.open_uart()?;
+        let result = self.set_framing(&mut uart, Framing::Wake);
+        if result.is_ok() {
+            let _ = uart.write(&[0]);
+        }
+        // Restore the persistent handle before propagating any error, so a
+        // failed framing switch does not silently force a reopen next call.
+        self.uart = Some(uart);
+        result?;
+
         thread::sleep(WAKE_DELAY);
         self.read_wake_response()
     }
 
     fn read_wake_response( &mut self) -> Result {
-        let port_name = &self.port;
-        let baud_rate = 230_400;
-        let stop_bits = StopBits::One;
-        let data_bits = DataBits::Seven;
-        let uart_cmd_builder = serialport::new(port_name, baud_rate)
-            .stop_bits(stop_bits)
-            .data_bits(data_bits);
-
-        let mut uart_cmd = uart_cmd_builder.open().unwrap_or_else(|e| {
-            eprintln!("Failed to open port {}. Error: {}", port_name,e);
-            ::std::process::exit(1);
-        });
-        
+        let mut uart = self.open_uart()?;
+        let result = self.read_wake_response_inner(&mut uart);
+        self.uart = Some(uart);
+        result
+    }
+
+    fn read_wake_response_inner(&mut self, uart: &mut Box<dyn SerialPort>) -> Result {
+        self.set_framing(uart, Framing::Command)?;
+
         // Send transmit flag to signal bus
         let mut transmit_flag = BytesMut::new();
         transmit_flag.put_u8(0x88);
         let encoded_transmit_flag = self.encode_uart_to_swi(&transmit_flag );
-        uart_cmd.write(&encoded_transmit_flag)?;
+        uart.write(&encoded_transmit_flag)?;
         thread::sleep(Duration::from_micros(5_000) );
-        
+
         let mut encoded_msg = BytesMut::new();
         encoded_msg.resize(40,0);
-        let _ = uart_cmd.read(&mut encoded_msg);
+        let _ = uart.read(&mut encoded_msg);
 
         let mut decoded_msg = BytesMut::new();
         decoded_msg.resize(5, 0);
-        
-        self.decode_swi_to_uart(&encoded_msg, &mut decoded_msg);
-        
-        let response = EccResponse::from_bytes(&decoded_msg[1..]);
-        match response {
-            Err(e) => return Err(e),
-            _ => return Ok(()),
+
+        self.decode_swi_to_uart(&encoded_msg, &mut decoded_msg)?;
+
+        match EccResponse::from_bytes(&decoded_msg[1..]) {
+            Err(e) => Err(e),
+            _ => Ok(()),
         }
     }
 
-    fn send_sleep(&mut self) {        
-        let port_name = &self.port;
-        let baud_rate = 230_400;
-        let stop_bits = StopBits::One;
-        let data_bits = DataBits::Seven;
-        let uart_cmd_builder = serialport::new(port_name, baud_rate)
-            .stop_bits(stop_bits)
-            .data_bits(data_bits);
+    fn send_sleep(&mut self) -> Result {
+        let mut uart = self.open_uart()?;
+        let result = self.send_sleep_inner(&mut uart);
+        self.uart = Some(uart);
+        result
+    }
 
-        let mut uart_cmd = uart_cmd_builder.open().unwrap_or_else(|e| {
-            eprintln!("Failed to open port {}. Error: {}", port_name,e);
-            ::std::process::exit(1);
-        });
+    fn send_sleep_inner(&mut self, uart: &mut Box<dyn SerialPort>) -> Result {
+        self.set_framing(uart, Framing::Command)?;
 
         let mut sleep_msg = BytesMut::new();
         sleep_msg.put_u8(0xCC);
         let sleep_encoded = self.encode_uart_to_swi(&sleep_msg);
 
-        let _ = uart_cmd.write(&sleep_encoded);
+        let _ = uart.write(&sleep_encoded);
+        Ok(())
     }
 
     pub(crate) fn send_command(&mut self, command: &EccCommand) -> Result<Bytes> {
@@ -293,7 +415,7 @@ impl Ecc {
             
             let response = EccResponse::from_bytes(&buf[..]);
             if sleep {
-                self.send_sleep();
+                let _ = self.send_sleep();
             }
             match response {
                 Ok(EccResponse::Data(bytes)) => return Ok(bytes),
@@ -316,35 +438,46 @@ impl Ecc {
         Err(Error::timeout())
     }
 
-    fn send_recv_buf(&mut self, delay: Duration, buf: &mut BytesMut) -> Result {
-        
-        let port_name = &self.port;
-        let baud_rate = 230_400;
-        let stop_bits = StopBits::One;
-        let data_bits = DataBits::Seven;
-        let uart_cmd_builder = serialport::new(port_name, baud_rate)
-            .stop_bits(stop_bits)
-            .data_bits(data_bits);
-
-        let mut uart_driver = uart_cmd_builder.open().unwrap_or_else(|e| {
-            eprintln!("Failed to open port {}. Error: {}", port_name,e);
-            ::std::process::exit(1);
-        });
-        
+    fn send_recv_buf(&mut self, deadline: Duration, buf: &mut BytesMut) -> Result {
+        let mut uart_driver = self.open_uart()?;
+        let result = self.transceive(deadline, buf, &mut uart_driver);
+        // Put the handle back on every path — including the error ones — so the
+        // persistent-handle promise holds and we never reopen per command.
+        self.uart = Some(uart_driver);
+        result
+    }
+
+    fn transceive(
+        &mut self,
+        deadline: Duration,
+        buf: &mut BytesMut,
+        uart_driver: &mut Box<dyn SerialPort>,
+    ) -> Result {
+        self.set_framing(uart_driver, Framing::Command)?;
+
         let _ = uart_driver.clear(ClearBuffer::All);
         let swi_msg = self.encode_uart_to_swi(buf);
-        self.send_buf(&swi_msg, &mut uart_driver)?;
-        thread::sleep(delay);
-        self.recv_buf(buf, &mut uart_driver)
+        self.send_buf(&swi_msg, uart_driver)?;
+        // No fixed pre-read sleep: `recv_buf` polls until the bus goes idle,
+        // bounded by `deadline` (the command's advertised duration).
+        self.recv_buf(buf, deadline, uart_driver)
     }
 
     pub(crate) fn send_buf(&mut self, buf: &[u8], serial_port: &mut Box<dyn SerialPort>) -> Result {
-        
+
+        self.emit_trace(TraceEvent::Send(buf));
         let send_size = serial_port.write(buf)?;
 
         //Each byte takes ~45us to transmit, so we must wait for the transmission to finish before proceeding
-        let uart_tx_time = Duration::from_micros( (buf.len() * 45) as u64); 
-        thread::sleep(uart_tx_time);
+        let uart_tx_time = Duration::from_micros( (buf.len() * 45) as u64);
+        // A long command drains for many milliseconds (a 32-byte write ≈ 14 ms):
+        // sleep the whole-millisecond bulk and only busy-wait the sub-millisecond
+        // remainder, so the drain never pins a core for the full transmit.
+        let bulk = Duration::from_millis(uart_tx_time.as_millis() as u64);
+        if !bulk.is_zero() {
+            thread::sleep(bulk);
+        }
+        self.spin_us(uart_tx_time - bulk);
         //Because Tx line is linked with Rx line, all sent msgs are returned on the Rx line and must be cleared from the buffer
         let mut clear_rx_line = BytesMut::new();
         clear_rx_line.resize(send_size, 0);
@@ -353,38 +486,100 @@ impl Ecc {
         Ok(())
     }
 
-    pub(crate) fn recv_buf(&mut self, buf: &mut BytesMut,  serial_port: &mut Box<dyn SerialPort>) -> Result {
+    pub(crate) fn recv_buf(&mut self, buf: &mut BytesMut, deadline: Duration, serial_port: &mut Box<dyn SerialPort>) -> Result {
         let mut encoded_msg = BytesMut::new();
         encoded_msg.resize(ATCA_CMD_SIZE_MAX as usize,0);
-        
+
         let mut transmit_flag = BytesMut::new();
         transmit_flag.put_u8(0x88);
         let encoded_transmit_flag = self.encode_uart_to_swi(&transmit_flag );
-        
-        let _ = serial_port.clear(ClearBuffer::All);
 
-        for retry in 0..RECV_RETRIES {
+        // The bus is considered idle once no new byte has arrived for two
+        // UART byte-times. That gap is only the *completion* threshold, checked
+        // against `last_byte.elapsed()`; the port itself gets a small blocking
+        // read timeout so the loop waits in `poll()` rather than busy-spinning.
+        let idle_gap = 2 * UART_BYTE_TIME;
+        let _ = serial_port.set_timeout(READ_POLL_TIMEOUT);
+
+        let start = Instant::now();
+        let mut received = 0usize;
+        'attempts: for _ in 0..RECV_RETRIES {
+            let _ = serial_port.clear(ClearBuffer::All);
             serial_port.write(&encoded_transmit_flag)?;
-            thread::sleep(Duration::from_micros(40_000) );
-            let read_response = serial_port.read(&mut encoded_msg);
-            
-            match read_response {
-                Ok(cnt) if cnt == 8 => { //If the buffer is empty except for the transmit flag, wait & try again
-                },
-                Ok(cnt) if cnt > 16 => {
+
+            received = 0;
+            let mut last_byte = Instant::now();
+            // The first 8 encoded bytes echo the transmit flag; the next 8
+            // decode to the frame's count byte, which tells us the full length.
+            let mut expected: Option<usize> = None;
+            loop {
+                match serial_port.read(&mut encoded_msg[received..]) {
+                    Ok(0) => {}
+                    Ok(cnt) => {
+                        received += cnt;
+                        last_byte = Instant::now();
+                    }
+                    // A plain timeout just means the line is momentarily quiet.
+                    Err(e) if e.kind() == std::io::ErrorKind::TimedOut => {}
+                    // Any other read error is a hard I/O fault (e.g. the device
+                    // went away); propagate the underlying `io::Error` as-is so
+                    // the caller can re-wake rather than retry blindly. We do
+                    // *not* relabel it parity/overrun: with default Linux termios
+                    // the tty layer never surfaces those as read errors.
+                    Err(e) => return Err(e.into()),
+                }
+
+                if received >= encoded_msg.len() {
+                    return Err(Error::UartOverrun);
+                }
+
+                if expected.is_none() && received >= 16 {
+                    let count = self.decode_count(&encoded_msg[8..16])?;
+                    if count as u16 > ATCA_CMD_SIZE_MAX / 8 {
+                        return Err(Error::Timeout);
+                    }
+                    expected = Some((count as usize + 1) * 8);
+                }
+
+                match expected {
+                    // All of the advertised bytes are in and the bus has gone
+                    // quiet: the frame is complete, return early.
+                    Some(total) if received >= total && last_byte.elapsed() >= idle_gap => {
+                        break 'attempts;
+                    }
+                    // Only the flag echo came back before the line went idle;
+                    // re-poll with a fresh transmit flag.
+                    None if received <= 8 && last_byte.elapsed() >= idle_gap => break,
+                    _ => {}
+                }
+
+                if start.elapsed() > deadline {
+                    if received > 16 {
+                        break 'attempts;
+                    }
                     break;
-                },
-                _ if retry != RECV_RETRIES => continue,
-                _  => return Err(Error::Timeout) 
+                }
+            }
+
+            if start.elapsed() > deadline {
+                break;
             }
-            
             thread::sleep(RECV_RETRY_WAIT);
         }
 
+        if received <= 16 {
+            return Err(Error::Timeout);
+        }
+        // Keep only whole bit-cells; a trailing partial cell is never a
+        // complete protocol byte.
+        let usable = received - (received % 8);
+        encoded_msg.truncate(usable);
+        self.emit_trace(TraceEvent::Recv(&encoded_msg[..]));
+
         let mut decoded_message = BytesMut::new();
-        decoded_message.resize((ATCA_CMD_SIZE_MAX) as usize, 0);   
+        decoded_message.resize(usable / 8, 0);
 
-        self.decode_swi_to_uart(&encoded_msg, &mut decoded_message);
+        self.decode_swi_to_uart(&encoded_msg, &mut decoded_message)?;
 
         let encoded_msg_size = decoded_message[1];
 
@@ -403,6 +598,18 @@ impl Ecc {
         Ok(())
     }
 
+    /// Decodes a single SWI bit-cell group (8 encoded UART bytes) back into the
+    /// one protocol byte it represents — used to peek at a frame's count byte
+    /// before the whole response has arrived.
+    fn decode_count(&mut self, cell: &[u8]) -> Result<u8> {
+        let mut src = BytesMut::with_capacity(8);
+        src.extend_from_slice(cell);
+        let mut out = BytesMut::new();
+        out.resize(1, 0);
+        self.decode_swi_to_uart(&src, &mut out)?;
+        Ok(out[0])
+    }
+
     fn encode_uart_to_swi(&mut self, uart_msg: &BytesMut ) -> BytesMut {
         
         let mut bit_field = BytesMut::new();
@@ -417,26 +624,83 @@ impl Ecc {
                 }
             }
         }
+        self.emit_trace(TraceEvent::Encode { uart: &uart_msg[..], swi: &bit_field[..] });
         bit_field
     }
     
-    fn decode_swi_to_uart(&mut self, swi_msg: &BytesMut, uart_msg: &mut BytesMut ) {
-    
+    fn decode_swi_to_uart(&mut self, swi_msg: &BytesMut, uart_msg: &mut BytesMut ) -> Result {
+
         uart_msg.clear();
         assert!( (swi_msg.len() % 8) == 0);
         uart_msg.resize( &swi_msg.len() / 8, 0 );
-    
-        let mut i = 0; 
+
+        let mut i = 0;
         for byte in uart_msg.iter_mut() {
             let bit_slice= &swi_msg[i..i+8];
-            
+
             for bit in bit_slice.iter(){
-                if *bit == 0x7F || *bit == 0x7E {
-                    *byte ^= 1;
+                match *bit {
+                    // A clean 7N1 mark level decodes to a logic `1`.
+                    0x7F | 0x7E => *byte ^= 1,
+                    // Any low level (including the echoed `0xFD` space and the
+                    // `0xFF` mark echoed for every transmitted `1` bit, e.g. in
+                    // the `[FD FD FD FF FD FD FD FF]` transmit-flag echo that
+                    // prefixes every frame) decodes to a logic `0`.
+                    b if b & 0x80 == 0 || b == 0xFD || b == 0xFF => {}
+                    // A high level that is neither a mark nor one of the echoed
+                    // cells means the start bit was sampled in the wrong place:
+                    // the 7N1 framing is broken and the decoded bits are garbage.
+                    _ => return Err(Error::UartFraming),
                 }
                 *byte = byte.rotate_right(1);
             }
             i += 8;
         }
+        self.emit_trace(TraceEvent::Decode { swi: &swi_msg[..], uart: &uart_msg[..] });
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_transmit_flag_echo() {
+        // The transmit-flag echo that prefixes every received frame carries the
+        // `0xFF` mark echoed for each transmitted `1` bit; decoding it must not
+        // raise `UartFraming` on otherwise healthy traffic.
+        let mut ecc = Ecc::from_path("/dev/null", 0).unwrap();
+        let swi = BytesMut::from(&[0xFD, 0xFD, 0xFD, 0xFF, 0xFD, 0xFD, 0xFD, 0xFF][..]);
+        let mut uart = BytesMut::new();
+        assert!(ecc.decode_swi_to_uart(&swi, &mut uart).is_ok());
+    }
+
+    /// Builds the eight SWI bit-cells a device drives for one protocol byte: a
+    /// 7N1 mark (logic `1`) reads back as `0x7F`, a space (logic `0`) reads low.
+    /// Cells are emitted LSB-first, matching `encode_uart_to_swi`.
+    fn device_cells(byte: u8) -> Vec<u8> {
+        (0..8)
+            .map(|i| if byte & (1 << i) != 0 { 0x7F } else { 0x00 })
+            .collect()
+    }
+
+    #[test]
+    fn count_cell_selects_variable_frame_length() {
+        // `recv_buf` reads the frame's count byte from the second bit-cell group
+        // (`encoded_msg[8..16]`, after the transmit-flag echo) and sizes the
+        // receive from it. A short status frame advertises count = 4; a 64-byte
+        // signature advertises count = 67 (1 count + 64 data + 2 CRC). Both must
+        // decode back to the advertised length so the idle loop knows when the
+        // frame is complete.
+        let mut ecc = Ecc::from_path("/dev/null", 0).unwrap();
+        for (count, encoded_len) in [(4u8, 40usize), (67u8, 544usize)] {
+            let mut head = BytesMut::new();
+            head.extend_from_slice(&device_cells(0x88)); // transmit-flag echo
+            head.extend_from_slice(&device_cells(count)); // count cell
+            let decoded = ecc.decode_count(&head[8..16]).unwrap();
+            assert_eq!(decoded, count);
+            assert_eq!((decoded as usize + 1) * 8, encoded_len);
+        }
     }
 }